@@ -0,0 +1,298 @@
+use std::{fmt, collections::HashMap};
+use crate::lexer;
+use crate::expr::{BinExpr, CallExpr, Expr, ExprKind, UnaryExpr};
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Null,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(x) => write!(f, "{}", x),
+            Value::Str(x) => write!(f, "{}", x),
+            Value::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EvalError {
+    TypeMismatch(String),
+    DivisionByZero,
+    Overflow,
+    UnknownVar(String),
+    UnknownFn(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::TypeMismatch(msg) => write!(f, "type mismatch: {}", msg),
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::Overflow => write!(f, "integer overflow"),
+            EvalError::UnknownVar(name) => write!(f, "unknown variable '{}'", name),
+            EvalError::UnknownFn(name) => write!(f, "unknown function '{}'", name),
+        }
+    }
+}
+
+pub fn eval(expr: &Expr, env: &HashMap<String, Value>) -> Result<Value, EvalError> {
+    match &expr.kind {
+        ExprKind::Int(x) => Ok(Value::Int(*x)),
+        ExprKind::Float(x) => Ok(Value::Float(*x)),
+        ExprKind::Bool(x) => Ok(Value::Bool(*x)),
+        ExprKind::Str(x) => Ok(Value::Str(x.clone())),
+        ExprKind::Null => Ok(Value::Null),
+        ExprKind::Var(name) => env.get(name).cloned()
+            .ok_or_else(|| EvalError::UnknownVar(name.clone())),
+        ExprKind::Unary(u) => eval_unary(u.op(), eval(u.operand(), env)?),
+        ExprKind::Bin(b) => {
+            let op = b.op();
+            if op == lexer::TOK_ANDAND || op == lexer::TOK_OROR {
+                return eval_short_circuit(op, b.lhs(), b.rhs(), env);
+            }
+            eval_bin(op, eval(b.lhs(), env)?, eval(b.rhs(), env)?)
+        }
+        ExprKind::Call(c) => {
+            let args: Result<Vec<Value>, EvalError> = c.args().iter()
+                .map(|a| eval(a, env)).collect();
+            eval_call(c.name(), args?)
+        }
+    }
+}
+
+fn eval_unary(op: i32, operand: Value) -> Result<Value, EvalError> {
+    match (op, operand) {
+        (x, Value::Int(v)) if x == '-' as i32 => v.checked_neg().map(Value::Int).ok_or(EvalError::Overflow),
+        (x, Value::Float(v)) if x == '-' as i32 => Ok(Value::Float(-v)),
+        (x, Value::Bool(v)) if x == '!' as i32 => Ok(Value::Bool(!v)),
+        (_, v) => Err(EvalError::TypeMismatch(
+            format!("unary operator cannot apply to {}", v))),
+    }
+}
+
+// Promotes mixed Int/Float operands to Float so `2 + 3.0` type-checks.
+fn as_numeric(v: &Value) -> Option<f64> {
+    match v {
+        Value::Int(x) => Some(*x as f64),
+        Value::Float(x) => Some(*x),
+        _ => None,
+    }
+}
+
+// `&&`/`||` short-circuit: the right-hand side is only evaluated when the
+// left-hand side doesn't already decide the result.
+fn eval_short_circuit(op: i32, lhs: &Expr, rhs: &Expr,
+    env: &HashMap<String, Value>) -> Result<Value, EvalError> {
+    let lhs = eval(lhs, env)?;
+    let lhs_bool = match &lhs {
+        Value::Bool(b) => *b,
+        v => return Err(EvalError::TypeMismatch(
+            format!("'&&'/'||' need bool operands, got {}", v))),
+    };
+    if op == lexer::TOK_ANDAND && !lhs_bool {return Ok(Value::Bool(false));}
+    if op == lexer::TOK_OROR && lhs_bool {return Ok(Value::Bool(true));}
+    match eval(rhs, env)? {
+        Value::Bool(b) => Ok(Value::Bool(b)),
+        v => Err(EvalError::TypeMismatch(format!("'&&'/'||' need bool operands, got {}", v))),
+    }
+}
+
+fn eval_bin(op: i32, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+    if op == lexer::TOK_ANDAND || op == lexer::TOK_OROR {
+        return match (lhs, rhs) {
+            (Value::Bool(a), Value::Bool(b)) if op == lexer::TOK_ANDAND => Ok(Value::Bool(a && b)),
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a || b)),
+            (a, b) => Err(EvalError::TypeMismatch(
+                format!("'&&'/'||' need bool operands, got {} and {}", a, b))),
+        };
+    }
+    if op == lexer::TOK_EQEQ || op == lexer::TOK_NE {
+        let eq = values_eq(&lhs, &rhs)?;
+        return Ok(Value::Bool(if op == lexer::TOK_EQEQ {eq} else {!eq}));
+    }
+    if let (Value::Int(a), Value::Int(b)) = (&lhs, &rhs) {
+        let (a, b) = (*a, *b);
+        return match op {
+            x if x == '+' as i32 => a.checked_add(b).map(Value::Int).ok_or(EvalError::Overflow),
+            x if x == '-' as i32 => a.checked_sub(b).map(Value::Int).ok_or(EvalError::Overflow),
+            x if x == '*' as i32 => a.checked_mul(b).map(Value::Int).ok_or(EvalError::Overflow),
+            x if x == '/' as i32 => if b == 0 {Err(EvalError::DivisionByZero)} else {Ok(Value::Int(a / b))},
+            x if x == '%' as i32 => if b == 0 {Err(EvalError::DivisionByZero)} else {Ok(Value::Int(a % b))},
+            x if x == '<' as i32 => Ok(Value::Bool(a < b)),
+            x if x == '>' as i32 => Ok(Value::Bool(a > b)),
+            x if x == lexer::TOK_LE => Ok(Value::Bool(a <= b)),
+            x if x == lexer::TOK_GE => Ok(Value::Bool(a >= b)),
+            _ => Err(EvalError::TypeMismatch("unsupported operator on ints".to_string())),
+        };
+    }
+    if let (Some(a), Some(b)) = (as_numeric(&lhs), as_numeric(&rhs)) {
+        return match op {
+            x if x == '+' as i32 => Ok(Value::Float(a + b)),
+            x if x == '-' as i32 => Ok(Value::Float(a - b)),
+            x if x == '*' as i32 => Ok(Value::Float(a * b)),
+            x if x == '/' as i32 => if b == 0.0 {Err(EvalError::DivisionByZero)} else {Ok(Value::Float(a / b))},
+            x if x == '%' as i32 => if b == 0.0 {Err(EvalError::DivisionByZero)} else {Ok(Value::Float(a % b))},
+            x if x == '<' as i32 => Ok(Value::Bool(a < b)),
+            x if x == '>' as i32 => Ok(Value::Bool(a > b)),
+            x if x == lexer::TOK_LE => Ok(Value::Bool(a <= b)),
+            x if x == lexer::TOK_GE => Ok(Value::Bool(a >= b)),
+            _ => Err(EvalError::TypeMismatch("unsupported operator on numbers".to_string())),
+        };
+    }
+    Err(EvalError::TypeMismatch(format!("cannot apply operator to {} and {}", lhs, rhs)))
+}
+
+fn values_eq(lhs: &Value, rhs: &Value) -> Result<bool, EvalError> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Ok(a == b),
+        (Value::Bool(a), Value::Bool(b)) => Ok(a == b),
+        (Value::Str(a), Value::Str(b)) => Ok(a == b),
+        (Value::Null, Value::Null) => Ok(true),
+        (a, b) => match (as_numeric(a), as_numeric(b)) {
+            (Some(a), Some(b)) => Ok(a == b),
+            _ => Err(EvalError::TypeMismatch(
+                format!("cannot compare {} and {} for equality", a, b))),
+        },
+    }
+}
+
+fn eval_call(name: &str, args: Vec<Value>) -> Result<Value, EvalError> {
+    match (name, args.as_slice()) {
+        ("abs", [Value::Int(x)]) => x.checked_abs().map(Value::Int).ok_or(EvalError::Overflow),
+        ("abs", [v]) if as_numeric(v).is_some() => Ok(Value::Float(as_numeric(v).unwrap().abs())),
+        ("abs", _) => Err(EvalError::TypeMismatch("abs expects one numeric argument".to_string())),
+        _ => Err(EvalError::UnknownFn(name.to_string())),
+    }
+}
+
+fn literal_value(expr: &Expr) -> Option<Value> {
+    match &expr.kind {
+        ExprKind::Int(x) => Some(Value::Int(*x)),
+        ExprKind::Float(x) => Some(Value::Float(*x)),
+        ExprKind::Bool(x) => Some(Value::Bool(*x)),
+        ExprKind::Str(x) => Some(Value::Str(x.clone())),
+        ExprKind::Null => Some(Value::Null),
+        _ => None,
+    }
+}
+
+fn value_to_kind(value: Value) -> ExprKind {
+    match value {
+        Value::Int(x) => ExprKind::Int(x),
+        Value::Float(x) => ExprKind::Float(x),
+        Value::Bool(x) => ExprKind::Bool(x),
+        Value::Str(x) => ExprKind::Str(x),
+        Value::Null => ExprKind::Null,
+    }
+}
+
+// Bottom-up rewrite that replaces any fully-literal `BinExpr`/`UnaryExpr`
+// subtree with its evaluated literal, leaving variable-dependent subtrees
+// (and anything that fails to evaluate, e.g. `1 / 0`) untouched.
+pub fn fold_constants(expr: Expr) -> Expr {
+    let span = expr.span;
+    match expr.kind {
+        ExprKind::Bin(b) => {
+            let (lhs, op, rhs) = b.into_parts();
+            let lhs = fold_constants(lhs);
+            let rhs = fold_constants(rhs);
+            if let (Some(lv), Some(rv)) = (literal_value(&lhs), literal_value(&rhs)) {
+                if let Ok(folded) = eval_bin(op, lv, rv) {
+                    return Expr::new(value_to_kind(folded), span);
+                }
+            }
+            Expr::new(ExprKind::Bin(Box::new(BinExpr::new(lhs, op, rhs))), span)
+        }
+        ExprKind::Unary(u) => {
+            let (op, operand) = u.into_parts();
+            let operand = fold_constants(operand);
+            if let Some(v) = literal_value(&operand) {
+                if let Ok(folded) = eval_unary(op, v) {
+                    return Expr::new(value_to_kind(folded), span);
+                }
+            }
+            Expr::new(ExprKind::Unary(Box::new(UnaryExpr::new(op, operand))), span)
+        }
+        ExprKind::Call(c) => {
+            let (name, args) = c.into_parts();
+            let args = args.into_iter().map(fold_constants).collect();
+            Expr::new(ExprKind::Call(Box::new(CallExpr::new(name, args))), span)
+        }
+        other => Expr::new(other, span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::parse_expr;
+    use crate::lexer::Lexer;
+
+    fn parse(src: &str) -> Expr {
+        let mut lexer = Lexer::new(src);
+        parse_expr(&mut lexer).unwrap()
+    }
+
+    #[test]
+    fn fold_constants_folds_fully_literal_subtrees() {
+        let folded = fold_constants(parse("2 + 3 * 4"));
+        assert_eq!(folded.to_string(), "IntExpr(14)");
+    }
+
+    #[test]
+    fn fold_constants_leaves_division_by_zero_unfolded() {
+        let folded = fold_constants(parse("1 / 0"));
+        assert_eq!(folded.to_string(), "BinExpr(IntExpr(1), Op(/), IntExpr(0))");
+    }
+
+    #[test]
+    fn eval_reports_division_by_zero() {
+        let env = HashMap::new();
+        let err = eval(&parse("1 / 0"), &env).unwrap_err();
+        assert!(matches!(err, EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn eval_short_circuits_and_without_evaluating_rhs() {
+        let env = HashMap::new();
+        // If `&&` evaluated the rhs eagerly this would fail with DivisionByZero.
+        let result = eval(&parse("false && (1 / 0)"), &env).unwrap();
+        assert!(matches!(result, Value::Bool(false)));
+    }
+
+    #[test]
+    fn eval_short_circuits_or_without_evaluating_rhs() {
+        let env = HashMap::new();
+        let result = eval(&parse("true || (1 / 0)"), &env).unwrap();
+        assert!(matches!(result, Value::Bool(true)));
+    }
+
+    #[test]
+    fn eval_reports_overflow_instead_of_panicking() {
+        let env = HashMap::new();
+        let err = eval(&parse("2147483647 + 1"), &env).unwrap_err();
+        assert!(matches!(err, EvalError::Overflow));
+    }
+
+    #[test]
+    fn eval_promotes_mixed_int_float_arithmetic_to_float() {
+        let env = HashMap::new();
+        let result = eval(&parse("2 + 3.5"), &env).unwrap();
+        assert!(matches!(result, Value::Float(v) if v == 5.5));
+    }
+
+    #[test]
+    fn fold_constants_folds_mixed_int_float_arithmetic() {
+        let folded = fold_constants(parse("2 + 3.5"));
+        assert_eq!(folded.to_string(), "FloatExpr(5.5)");
+    }
+}
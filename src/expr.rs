@@ -1,5 +1,5 @@
-use std::{fmt, collections::HashMap};
-use crate::lexer::{self, Lexer};
+use std::fmt;
+use crate::lexer::{self, Lexer, Span};
 
 pub struct BinExpr {
     lhs: Expr,
@@ -12,45 +12,131 @@ pub struct CallExpr {
     args: Vec<Expr>,
 }
 
-pub enum Expr {
+pub struct UnaryExpr {
+    op: i32,
+    operand: Expr,
+}
+
+pub enum ExprKind {
     Str(String),
     Int(i32),
+    Float(f64),
     Bool(bool),
     Var(String),
     Bin(Box<BinExpr>),
+    Unary(Box<UnaryExpr>),
     Call(Box<CallExpr>),
     Null,
 }
 
-fn get_op_prec(op: i32) -> i32 {
+// An `ExprKind` together with the byte range of source it was parsed from,
+// so diagnostics can point at the exact offending text.
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self{kind: kind, span: span}
+    }
+}
+
+// A recoverable parse failure, tagged with the source range it was raised for.
+#[derive(Debug)]
+pub enum ParseError {
+    UnexpectedChar(Span),
+    ExpectedExpr(Span),
+    MissingRParen(Span),
+    MissingComma(Span),
+    UnknownOperator(Span),
+    UnexpectedEof(Span),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar(s) => write!(f, "unexpected character at {}..{}", s.start, s.end),
+            ParseError::ExpectedExpr(s) => write!(f, "expected an expression at {}..{}", s.start, s.end),
+            ParseError::MissingRParen(s) => write!(f, "missing closing ')' at {}..{}", s.start, s.end),
+            ParseError::MissingComma(s) => write!(f, "missing ',' at {}..{}", s.start, s.end),
+            ParseError::UnknownOperator(s) => write!(f, "unknown operator at {}..{}", s.start, s.end),
+            ParseError::UnexpectedEof(s) => write!(f, "unexpected end of input at {}..{}", s.start, s.end),
+        }
+    }
+}
+
+// Checks that the current token has `kind`, producing `on_mismatch` otherwise.
+fn assert_token_kind(lexer: &Lexer, kind: i32,
+    on_mismatch: impl Fn(Span) -> ParseError) -> Result<(), ParseError> {
+    if lexer.empty() {
+        return Err(ParseError::UnexpectedEof(lexer.eof_span()));
+    }
+    if lexer.front().kind != kind {
+        return Err(on_mismatch(lexer.front_span()));
+    }
+    Ok(())
+}
+
+fn assert_token(lexer: &Lexer) -> Result<(), ParseError> {
+    if lexer.empty() {
+        return Err(ParseError::UnexpectedEof(lexer.eof_span()));
+    }
+    Ok(())
+}
+
+// (precedence, right-associative?) for every binary operator the parser knows.
+fn get_op_prec(op: i32) -> (i32, bool) {
     match op {
-        x if x == '*' as i32 => 40,
-        x if x == '/' as i32 => 40,
-        x if x == '+' as i32 => 20,
-        x if x == '-' as i32 => 20,
-        _ => -1,
+        x if x == '%' as i32 => (40, false),
+        x if x == '*' as i32 => (40, false),
+        x if x == '/' as i32 => (40, false),
+        x if x == '+' as i32 => (20, false),
+        x if x == '-' as i32 => (20, false),
+        x if x == '<' as i32 => (10, false),
+        x if x == '>' as i32 => (10, false),
+        lexer::TOK_LE => (10, false),
+        lexer::TOK_GE => (10, false),
+        lexer::TOK_EQEQ => (10, false),
+        lexer::TOK_NE => (10, false),
+        lexer::TOK_ANDAND => (6, false),
+        lexer::TOK_OROR => (4, false),
+        x if x == '=' as i32 => (2, true),
+        _ => (-1, false),
     }
 }
 
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self {
-            Expr::Str(x) => write!(f, "StrExpr(\"{}\")", x),
-            Expr::Int(x) => write!(f, "IntExpr({})", x),
-            Expr::Bool(x) => write!(f, "BoolExpr({})", x),
-            Expr::Var(x) => write!(f, "VarExpr({})", x),
-            Expr::Bin(x) => write!(f, "{}", x),
-            Expr::Call(x) => write!(f, "{}", x),
-            Expr::Null => write!(f, "NullExpr()"),
+        match &self.kind {
+            ExprKind::Str(x) => write!(f, "StrExpr(\"{}\")", x),
+            ExprKind::Int(x) => write!(f, "IntExpr({})", x),
+            ExprKind::Float(x) => write!(f, "FloatExpr({})", x),
+            ExprKind::Bool(x) => write!(f, "BoolExpr({})", x),
+            ExprKind::Var(x) => write!(f, "VarExpr({})", x),
+            ExprKind::Bin(x) => write!(f, "{}", x),
+            ExprKind::Unary(x) => write!(f, "{}", x),
+            ExprKind::Call(x) => write!(f, "{}", x),
+            ExprKind::Null => write!(f, "NullExpr()"),
         }
     }
 }
 
+fn op_to_str(op: i32) -> String {
+    match op {
+        lexer::TOK_LE => "<=".to_string(),
+        lexer::TOK_GE => ">=".to_string(),
+        lexer::TOK_EQEQ => "==".to_string(),
+        lexer::TOK_NE => "!=".to_string(),
+        lexer::TOK_ANDAND => "&&".to_string(),
+        lexer::TOK_OROR => "||".to_string(),
+        x => std::char::from_u32(x.try_into().unwrap()).unwrap().to_string(),
+    }
+}
+
 impl fmt::Display for BinExpr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "BinExpr({}, Op({}), {})", self.lhs, 
-            std::char::from_u32(self.op.try_into()
-                .unwrap()).unwrap(), self.rhs)
+        write!(f, "BinExpr({}, Op({}), {})", self.lhs, op_to_str(self.op), self.rhs)
     }
 }
 
@@ -58,6 +144,46 @@ impl BinExpr {
     pub fn new(lhs: Expr, op: i32, rhs: Expr) -> Self {
         Self{lhs: lhs, op: op, rhs: rhs}
     }
+
+    pub(crate) fn lhs(&self) -> &Expr {
+        &self.lhs
+    }
+
+    pub(crate) fn op(&self) -> i32 {
+        self.op
+    }
+
+    pub(crate) fn rhs(&self) -> &Expr {
+        &self.rhs
+    }
+
+    pub(crate) fn into_parts(self) -> (Expr, i32, Expr) {
+        (self.lhs, self.op, self.rhs)
+    }
+}
+
+impl fmt::Display for UnaryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UnaryExpr(Op({}), {})", op_to_str(self.op), self.operand)
+    }
+}
+
+impl UnaryExpr {
+    pub fn new(op: i32, operand: Expr) -> Self {
+        Self{op: op, operand: operand}
+    }
+
+    pub(crate) fn op(&self) -> i32 {
+        self.op
+    }
+
+    pub(crate) fn operand(&self) -> &Expr {
+        &self.operand
+    }
+
+    pub(crate) fn into_parts(self) -> (i32, Expr) {
+        (self.op, self.operand)
+    }
 }
 
 impl fmt::Display for CallExpr {
@@ -74,123 +200,308 @@ impl CallExpr {
     pub fn new(name: String, args: Vec<Expr>) -> Self {
         CallExpr{name: name, args: args}
     }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn args(&self) -> &[Expr] {
+        &self.args
+    }
+
+    pub(crate) fn into_parts(self) -> (String, Vec<Expr>) {
+        (self.name, self.args)
+    }
 }
 
-pub fn parse_num_expr(lexer: &mut Lexer) -> Option<Expr> {
-    lexer.assert_token();
-    let expr: Option<Expr> = match lexer.front()
-        .get_int_val() {
-            Some(x) => Some(Expr::Int(*x)),
-            None => None,
-        };
+pub fn parse_num_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token(lexer)?;
+    let span = lexer.front_span();
+    let kind = if lexer.front().kind == lexer::TOK_FLOAT {
+        ExprKind::Float(*lexer.front().get_float_val()
+            .ok_or(ParseError::ExpectedExpr(span))?)
+    } else {
+        ExprKind::Int(*lexer.front().get_int_val()
+            .ok_or(ParseError::ExpectedExpr(span))?)
+    };
     lexer.eat();
-    expr
+    Ok(Expr::new(kind, span))
 }
 
-pub fn parse_str_expr(lexer: &mut Lexer) -> Option<Expr> {
-    lexer.assert_token();
-    let expr: Option<Expr> = match lexer.front()
-        .get_str_val() {
-            Some(x) => Some(Expr::Str(x.to_string())),
-            None => None,
-        };
+pub fn parse_str_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token(lexer)?;
+    let span = lexer.front_span();
+    let val = lexer.front().get_str_val()
+        .ok_or(ParseError::ExpectedExpr(span))?.to_string();
     lexer.eat();
-    expr
+    Ok(Expr::new(ExprKind::Str(val), span))
 }
 
-pub fn parse_bool_expr(lexer: &mut Lexer) -> Option<Expr> {
-    lexer.assert_token();
-    let expr: Option<Expr> = match lexer.front()
-        .get_bool_val() {
-            Some(x) => Some(Expr::Bool(*x)),
-            None => None,
-        };
+pub fn parse_bool_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token(lexer)?;
+    let span = lexer.front_span();
+    let val = *lexer.front().get_bool_val()
+        .ok_or(ParseError::ExpectedExpr(span))?;
     lexer.eat();
-    expr
+    Ok(Expr::new(ExprKind::Bool(val), span))
 }
 
-pub fn parse_paren_expr(lexer: &mut Lexer) -> Option<Expr> {
-    lexer.assert_token_kind('(' as i32);
+pub fn parse_paren_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token_kind(lexer, '(' as i32, ParseError::ExpectedExpr)?;
+    let lparen_span = lexer.front_span();
     lexer.eat(); // eat '('
-    let expr: Option<Expr> = parse_expr(lexer);
-    lexer.assert_token_kind(')' as i32);
+    let expr: Expr = parse_expr(lexer)?;
+    assert_token_kind(lexer, ')' as i32, ParseError::MissingRParen)?;
+    let rparen_span = lexer.front_span();
     lexer.eat(); // eat ')'
-    expr
+    Ok(Expr::new(expr.kind, lparen_span.to(rparen_span)))
 }
 
 // reference: https://llvm.org/docs/tutorial/MyFirstLanguageFrontend/LangImpl02.html
-pub fn parse_bin_rhs(lexer: &mut Lexer, prec: i32, lhs: Expr) -> Option<Expr> {
+pub fn parse_bin_rhs(lexer: &mut Lexer, prec: i32, lhs: Expr) -> Result<Expr, ParseError> {
+    let mut lhs = lhs;
     loop {
-        if lexer.empty() {return Some(lhs);}
+        if lexer.empty() {return Ok(lhs);}
         let bin_op: i32 = lexer.front().kind;
-        let op_prec: i32 =  get_op_prec(bin_op);
-        if op_prec < prec {return Some(lhs);}
+        let (op_prec, _): (i32, bool) = get_op_prec(bin_op);
+        if op_prec < prec {return Ok(lhs);}
         lexer.eat(); // eat bin_op
-        let mut rhs: Expr = match parse_primary_expr(lexer) {
-            Some(x) => Some(x),
-            None => {
-                lexer.error("expected a valid expr"
-                    .to_string(), None); None
+        let mut rhs: Expr = parse_unary_expr(lexer)?;
+        if !lexer.empty() {
+            let next_op: i32 = lexer.front().kind;
+            let (next_prec, next_right_assoc): (i32, bool) = get_op_prec(next_op);
+            if op_prec < next_prec || (next_right_assoc && op_prec == next_prec) {
+                let next_threshold = if next_right_assoc {op_prec} else {op_prec + 1};
+                rhs = parse_bin_rhs(lexer, next_threshold, rhs)?;
             }
-        }.unwrap();
-        if lexer.empty() {
-            return Some(Expr::Bin(Box::new(BinExpr::new(lhs, bin_op, rhs))));
-        }
-        let next_op: i32 = lexer.front().kind;
-        let next_prec: i32 = get_op_prec(next_op);
-        if op_prec < next_prec {
-            rhs = match parse_bin_rhs(lexer, op_prec+1, rhs) {
-                Some(x) => Some(x),
-                None => {
-                    lexer.error("expected a valid expr"
-                        .to_string(), None); None
-                }
-            }.unwrap();
         }
-        return Some(Expr::Bin(Box::new(BinExpr::new(lhs, bin_op, rhs))));    
+        let span = lhs.span.to(rhs.span);
+        // Fold into `lhs` and keep scanning for the next operator at this
+        // precedence level instead of returning after a single pair.
+        lhs = Expr::new(ExprKind::Bin(Box::new(BinExpr::new(lhs, bin_op, rhs))), span);
     }
 }
 
-pub fn parse_iden(lexer: &mut Lexer) -> Option<Expr> {
-    lexer.assert_token();
+pub fn parse_iden(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token(lexer)?;
+    let name_span = lexer.front_span();
     let name: String = lexer.front().get_str_val()
-        .unwrap().to_string();
+        .ok_or(ParseError::ExpectedExpr(name_span))?.to_string();
     lexer.eat(); // eat name
     if lexer.empty() || !lexer.is_token_kind('(' as i32) {
-        return Some(Expr::Var(name));
+        return Ok(Expr::new(ExprKind::Var(name), name_span));
     }
     lexer.eat(); // eat '('
     let mut args: Vec<Expr> = Vec::new();
     while !lexer.empty() && !lexer.is_token_kind(')' as i32) {
-        match parse_expr(lexer) {
-            Some(x) => args.push(x),
-            None => lexer.error(
-                String::from("expected correct expr"), None),
-        };
-        if !lexer.empty() && 
+        args.push(parse_expr(lexer)?);
+        if !lexer.empty() &&
             lexer.is_token_kind(')' as i32) {break;}
-        lexer.assert_token_kind(',' as i32);
+        assert_token_kind(lexer, ',' as i32, ParseError::MissingComma)?;
         lexer.eat(); // eat ','
     }
+    assert_token_kind(lexer, ')' as i32, ParseError::MissingRParen)?;
+    let rparen_span = lexer.front_span();
     lexer.eat(); // eat ')'
-    Some(Expr::Call(Box::new(CallExpr{name: name, args: args})))
+    Ok(Expr::new(ExprKind::Call(Box::new(CallExpr{name: name, args: args})),
+        name_span.to(rparen_span)))
 }
 
-fn parse_primary_expr(lexer: &mut Lexer) -> Option<Expr> {
-    lexer.assert_token();
+// Prefix operators (`-`, `!`) sit between `parse_bin_rhs` and `parse_primary_expr`
+// so that e.g. `a - -b` and `!a && !b` parse.
+fn parse_unary_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token(lexer)?;
+    let op = lexer.front().kind;
+    if op == '-' as i32 || op == '!' as i32 {
+        let op_span = lexer.front_span();
+        lexer.eat(); // eat unary op
+        let operand = parse_unary_expr(lexer)?;
+        let span = op_span.to(operand.span);
+        return Ok(Expr::new(ExprKind::Unary(Box::new(UnaryExpr::new(op, operand))), span));
+    }
+    parse_primary_expr(lexer)
+}
+
+fn parse_primary_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    assert_token(lexer)?;
     match lexer.front().kind {
-        lexer::TOK_INT => parse_num_expr(lexer),
+        lexer::TOK_INT | lexer::TOK_FLOAT => parse_num_expr(lexer),
         lexer::TOK_STR_LIT => parse_str_expr(lexer),
         lexer::TOK_BOOL => parse_bool_expr(lexer),
         lexer::TOK_IDEN => parse_iden(lexer),
         x if x == '(' as i32 => parse_paren_expr(lexer),
-        _ => Some(Expr::Null)   
+        _ => Err(ParseError::UnexpectedChar(lexer.front_span())),
     }
 }
 
-pub fn parse_expr(lexer: &mut Lexer) -> Option<Expr> {
-    match parse_primary_expr(lexer) {
-        Some(x) => parse_bin_rhs(lexer, 0, x),
-        None => None,
+pub fn parse_expr(lexer: &mut Lexer) -> Result<Expr, ParseError> {
+    let lhs = parse_unary_expr(lexer)?;
+    parse_bin_rhs(lexer, 0, lhs)
+}
+
+// Skips tokens until a synchronizing point (a statement terminator, which is
+// consumed, or a `)`/`,` left for the caller) so one bad expression doesn't
+// abort the whole parse.
+fn recover(lexer: &mut Lexer) {
+    while !lexer.empty() {
+        let kind = lexer.front().kind;
+        if kind == ';' as i32 {
+            lexer.eat();
+            return;
+        }
+        if kind == ')' as i32 || kind == ',' as i32 {
+            return;
+        }
+        lexer.eat();
+    }
+}
+
+// Parses a whole program as a sequence of `;`-terminated expressions,
+// recovering from each error in panic mode so multiple diagnostics can be
+// reported from a single run instead of aborting at the first one.
+pub fn parse_program(lexer: &mut Lexer) -> (Vec<Expr>, Vec<ParseError>) {
+    let mut exprs: Vec<Expr> = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+    while !lexer.empty() {
+        match parse_expr(lexer) {
+            Ok(expr) => exprs.push(expr),
+            Err(err) => {
+                errors.push(err);
+                let pos_before = lexer.pos();
+                recover(lexer);
+                // `recover` deliberately leaves a stray `)`/`,` for a caller
+                // further up the call stack, but at the top level there is no
+                // such caller waiting for it — eat it ourselves so a bare `)`
+                // can't stall the loop forever.
+                if !lexer.empty() && lexer.pos() == pos_before {
+                    lexer.eat();
+                }
+                continue;
+            }
+        }
+        if !lexer.empty() && lexer.is_token_kind(';' as i32) {
+            lexer.eat(); // eat ';'
+        }
+    }
+    (exprs, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bin_rhs_chains_left_associative_ops_at_same_precedence() {
+        let mut lexer = Lexer::new("a - b - c");
+        let (exprs, errors) = parse_program(&mut lexer);
+        assert!(errors.is_empty());
+        assert_eq!(exprs.len(), 1);
+        assert_eq!(
+            exprs[0].to_string(),
+            "BinExpr(BinExpr(VarExpr(a), Op(-), VarExpr(b)), Op(-), VarExpr(c))"
+        );
+    }
+
+    #[test]
+    fn bin_rhs_chains_arithmetic_and_logical_ops() {
+        let mut lexer = Lexer::new("1 + 2 + 3 + 4");
+        let (exprs, errors) = parse_program(&mut lexer);
+        assert!(errors.is_empty());
+        assert_eq!(exprs.len(), 1);
+
+        let mut lexer = Lexer::new("a && b && c");
+        let (exprs, errors) = parse_program(&mut lexer);
+        assert!(errors.is_empty());
+        assert_eq!(exprs.len(), 1);
+    }
+
+    #[test]
+    fn parse_program_terminates_on_stray_rparen() {
+        let mut lexer = Lexer::new(")");
+        let (exprs, errors) = parse_program(&mut lexer);
+        assert!(exprs.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_program_recovers_past_stray_rparen_after_valid_expr() {
+        let mut lexer = Lexer::new("1 + 2)");
+        let (exprs, errors) = parse_program(&mut lexer);
+        assert_eq!(exprs.len(), 1);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_program_reports_error_instead_of_panicking_on_overflow() {
+        let mut lexer = Lexer::new("99999999999999999999 + 1");
+        let (exprs, errors) = parse_program(&mut lexer);
+        assert!(exprs.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], ParseError::ExpectedExpr(_)));
+    }
+
+    #[test]
+    fn assign_is_right_associative() {
+        let mut lexer = Lexer::new("a = b = c");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.to_string(), "BinExpr(VarExpr(a), Op(=), BinExpr(VarExpr(b), Op(=), VarExpr(c)))");
+    }
+
+    #[test]
+    fn comparison_and_percent_respect_precedence() {
+        let mut lexer = Lexer::new("a % b < c == d");
+        let expr = parse_expr(&mut lexer).unwrap();
+        // `%` binds tighter than `<`; `<` and `==` share a precedence level
+        // and group left to right.
+        assert_eq!(
+            expr.to_string(),
+            "BinExpr(BinExpr(BinExpr(VarExpr(a), Op(%), VarExpr(b)), Op(<), VarExpr(c)), Op(==), VarExpr(d))"
+        );
+    }
+
+    #[test]
+    fn float_literal_parses_as_float_expr() {
+        let mut lexer = Lexer::new("3.14");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.to_string(), "FloatExpr(3.14)");
+    }
+
+    #[test]
+    fn float_literal_with_exponent_parses_as_float_expr() {
+        let mut lexer = Lexer::new("1e3");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.to_string(), "FloatExpr(1000)");
+    }
+
+    #[test]
+    fn paren_expr_span_covers_parens_not_just_inner_expr() {
+        let mut lexer = Lexer::new("(1 + 2)");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.span, Span::new(0, 7));
+    }
+
+    #[test]
+    fn call_expr_span_covers_name_through_closing_paren() {
+        let mut lexer = Lexer::new("foo(1, 2)");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.span, Span::new(0, 9));
+    }
+
+    #[test]
+    fn unary_expr_parses_negation_and_not() {
+        let mut lexer = Lexer::new("-5");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.to_string(), "UnaryExpr(Op(-), IntExpr(5))");
+
+        let mut lexer = Lexer::new("!flag");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.to_string(), "UnaryExpr(Op(!), VarExpr(flag))");
+    }
+
+    #[test]
+    fn unary_minus_chains_with_binary_minus() {
+        let mut lexer = Lexer::new("a - -b");
+        let expr = parse_expr(&mut lexer).unwrap();
+        assert_eq!(expr.to_string(), "BinExpr(VarExpr(a), Op(-), UnaryExpr(Op(-), VarExpr(b)))");
     }
 }
@@ -0,0 +1,201 @@
+pub const TOK_INT: i32 = 256;
+pub const TOK_STR_LIT: i32 = 257;
+pub const TOK_BOOL: i32 = 258;
+pub const TOK_IDEN: i32 = 259;
+pub const TOK_LE: i32 = 260; // <=
+pub const TOK_GE: i32 = 261; // >=
+pub const TOK_EQEQ: i32 = 262; // ==
+pub const TOK_NE: i32 = 263; // !=
+pub const TOK_ANDAND: i32 = 264; // &&
+pub const TOK_OROR: i32 = 265; // ||
+pub const TOK_FLOAT: i32 = 266;
+
+// Byte offsets into the source text a node or token was parsed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self{start: start, end: end}
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
+pub struct Token {
+    pub kind: i32,
+    pub span: Span,
+    int_val: Option<i32>,
+    float_val: Option<f64>,
+    str_val: Option<String>,
+    bool_val: Option<bool>,
+}
+
+impl Token {
+    fn op(kind: i32, span: Span) -> Self {
+        Self{kind: kind, span: span, int_val: None, float_val: None, str_val: None, bool_val: None}
+    }
+
+    // `val` is `None` when the literal's text didn't fit in an `i32` (the
+    // parser turns that into a `ParseError` rather than this lexer panicking).
+    fn int(val: Option<i32>, span: Span) -> Self {
+        Self{kind: TOK_INT, span: span, int_val: val, float_val: None, str_val: None, bool_val: None}
+    }
+
+    fn float(x: f64, span: Span) -> Self {
+        Self{kind: TOK_FLOAT, span: span, int_val: None, float_val: Some(x), str_val: None, bool_val: None}
+    }
+
+    fn str_lit(x: String, span: Span) -> Self {
+        Self{kind: TOK_STR_LIT, span: span, int_val: None, float_val: None, str_val: Some(x), bool_val: None}
+    }
+
+    fn bool_lit(x: bool, span: Span) -> Self {
+        Self{kind: TOK_BOOL, span: span, int_val: None, float_val: None, str_val: None, bool_val: Some(x)}
+    }
+
+    fn iden(x: String, span: Span) -> Self {
+        Self{kind: TOK_IDEN, span: span, int_val: None, float_val: None, str_val: Some(x), bool_val: None}
+    }
+
+    pub fn get_int_val(&self) -> Option<&i32> {
+        self.int_val.as_ref()
+    }
+
+    pub fn get_float_val(&self) -> Option<&f64> {
+        self.float_val.as_ref()
+    }
+
+    pub fn get_str_val(&self) -> Option<&String> {
+        self.str_val.as_ref()
+    }
+
+    pub fn get_bool_val(&self) -> Option<&bool> {
+        self.bool_val.as_ref()
+    }
+}
+
+pub struct Lexer {
+    tokens: Vec<Token>,
+    pos: usize,
+    len: usize,
+}
+
+impl Lexer {
+    pub fn new(src: &str) -> Self {
+        Self{tokens: tokenize(src), pos: 0, len: src.chars().count()}
+    }
+
+    pub fn front(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    pub fn front_span(&self) -> Span {
+        self.front().span
+    }
+
+    // Span to blame when the parser needed a token but ran out of input.
+    pub fn eof_span(&self) -> Span {
+        Span::new(self.len, self.len)
+    }
+
+    pub fn eat(&mut self) {
+        self.pos += 1;
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    pub fn is_token_kind(&self, kind: i32) -> bool {
+        !self.empty() && self.front().kind == kind
+    }
+
+    pub fn error(&self, msg: String, span: Option<Span>) {
+        match span {
+            Some(s) => eprintln!("error at {}..{}: {}", s.start, s.end, msg),
+            None => eprintln!("error: {}", msg),
+        }
+    }
+}
+
+fn tokenize(src: &str) -> Vec<Token> {
+    // `src` is assumed ASCII, so char index == byte offset.
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {i += 1;}
+            let mut is_float = false;
+            if chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()) {
+                is_float = true;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {i += 1;}
+            }
+            if matches!(chars.get(i), Some('e') | Some('E')) {
+                let mut j = i + 1;
+                if matches!(chars.get(j), Some('+') | Some('-')) {j += 1;}
+                if chars.get(j).is_some_and(|d| d.is_ascii_digit()) {
+                    is_float = true;
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {i += 1;}
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let span = Span::new(start, i);
+            if is_float {
+                tokens.push(Token::float(text.parse().unwrap(), span));
+            } else {
+                tokens.push(Token::int(text.parse().ok(), span));
+            }
+        } else if c == '"' {
+            let start = i;
+            i += 1;
+            let lit_start = i;
+            while i < chars.len() && chars[i] != '"' {i += 1;}
+            let val: String = chars[lit_start..i].iter().collect();
+            i += 1; // eat closing quote
+            tokens.push(Token::str_lit(val, Span::new(start, i)));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {i += 1;}
+            let word: String = chars[start..i].iter().collect();
+            let span = Span::new(start, i);
+            match word.as_str() {
+                "true" => tokens.push(Token::bool_lit(true, span)),
+                "false" => tokens.push(Token::bool_lit(false, span)),
+                _ => tokens.push(Token::iden(word, span)),
+            }
+        } else {
+            let next = chars.get(i + 1).copied();
+            let two_char = match (c, next) {
+                ('<', Some('=')) => Some(TOK_LE),
+                ('>', Some('=')) => Some(TOK_GE),
+                ('=', Some('=')) => Some(TOK_EQEQ),
+                ('!', Some('=')) => Some(TOK_NE),
+                ('&', Some('&')) => Some(TOK_ANDAND),
+                ('|', Some('|')) => Some(TOK_OROR),
+                _ => None,
+            };
+            match two_char {
+                Some(kind) => {tokens.push(Token::op(kind, Span::new(i, i + 2))); i += 2;}
+                None => {tokens.push(Token::op(c as i32, Span::new(i, i + 1))); i += 1;}
+            }
+        }
+    }
+    tokens
+}